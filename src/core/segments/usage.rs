@@ -1,9 +1,12 @@
 use super::{Segment, SegmentData};
 use crate::config::{InputData, SegmentId};
 use crate::utils::credentials;
-use chrono::{DateTime, Datelike, Duration, Local, Timelike, Utc};
+use chrono::{DateTime, Duration as ChronoDuration, Local, Timelike, Utc};
+use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration as StdDuration, Instant};
+use zeroize::Zeroize;
 
 #[derive(Debug, Deserialize)]
 struct ApiUsageResponse {
@@ -29,11 +32,47 @@ struct ApiUsageCache {
     #[serde(default, rename = "resets_at", skip_serializing)]
     legacy_resets_at: Option<String>,
     cached_at: String,
+    /// Set when a fetch (by the background daemon or by `collect`'s
+    /// synchronous fallback) could not refresh an expired access token, so
+    /// the renderer can prompt the user to re-login.
+    #[serde(default)]
+    auth_expired: bool,
+    /// Bounded history of recent samples, oldest first, used to render the
+    /// `trend` sparkline.
+    #[serde(default)]
+    samples: Vec<UsageSample>,
+    /// Validators from the last 200 response, sent back as `If-None-Match`
+    /// / `If-Modified-Since` so an unchanged usage document costs a 304
+    /// instead of a full body.
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsageSample {
+    cached_at: String,
+    five_hour_utilization: f64,
+    seven_day_utilization: f64,
 }
 
+/// Anthropic's OAuth token endpoint. This lives on `console.anthropic.com`,
+/// a different host from `api_base_url` (which points at the usage API), so
+/// it is never derived from it.
+const OAUTH_TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
+
+/// Claude Code's public OAuth client id, required on every token exchange
+/// (including refresh) against `OAUTH_TOKEN_URL`.
+const OAUTH_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+
 #[derive(Default)]
 pub struct UsageSegment;
 
+/// Matches the historical hard-coded `month-day-hour` layout, so leaving
+/// `reset_time_pattern` unset keeps the same layout as before.
+const DEFAULT_RESET_TIME_PATTERN: &str = "%-m-%-d-%-H";
+
 #[derive(Debug, Clone, Copy, Default)]
 enum ResetPeriod {
     #[default]
@@ -69,6 +108,7 @@ enum ResetFormat {
     #[default]
     Time,
     Duration,
+    Trend,
 }
 
 impl ResetFormat {
@@ -76,6 +116,7 @@ impl ResetFormat {
         match self {
             Self::Time => "time",
             Self::Duration => "duration",
+            Self::Trend => "trend",
         }
     }
 }
@@ -88,6 +129,8 @@ impl TryFrom<&str> for ResetFormat {
             Ok(Self::Time)
         } else if value.eq_ignore_ascii_case("duration") {
             Ok(Self::Duration)
+        } else if value.eq_ignore_ascii_case("trend") {
+            Ok(Self::Trend)
         } else {
             Err(())
         }
@@ -113,22 +156,55 @@ impl UsageSegment {
         }
     }
 
-    fn format_reset_time(reset_time_str: Option<&str>) -> String {
-        if let Some(time_str) = reset_time_str {
-            if let Ok(dt) = DateTime::parse_from_rfc3339(time_str) {
-                let mut local_dt = dt.with_timezone(&Local);
-                if local_dt.minute() > 45 {
-                    local_dt += Duration::hours(1);
+    /// Resolves a `reset_timezone` option value ("utc" or an IANA name) to a
+    /// concrete zone. Returns `Err(())` for an unrecognised name; an unset
+    /// option never reaches here, and callers fall back to the system-local
+    /// zone for it.
+    fn resolve_timezone(raw: &str) -> Result<chrono_tz::Tz, ()> {
+        if raw.eq_ignore_ascii_case("utc") {
+            Ok(chrono_tz::UTC)
+        } else {
+            raw.parse::<chrono_tz::Tz>().map_err(|_| ())
+        }
+    }
+
+    /// Mirrors `resolve_timezone`'s mistake-detection for strftime patterns:
+    /// an invalid directive turns into a `chrono::format::Item::Error`
+    /// instead of a parse error, so we scan for that instead of a `Result`.
+    fn is_valid_time_pattern(pattern: &str) -> bool {
+        use chrono::format::{Item, StrftimeItems};
+        !StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error))
+    }
+
+    fn format_reset_time(
+        reset_time_str: Option<&str>,
+        timezone: Option<chrono_tz::Tz>,
+        pattern: &str,
+        round_up: bool,
+    ) -> String {
+        let Some(time_str) = reset_time_str else {
+            return "?".to_string();
+        };
+        let Ok(dt) = DateTime::parse_from_rfc3339(time_str) else {
+            return "?".to_string();
+        };
+
+        match timezone {
+            Some(tz) => {
+                let mut zoned = dt.with_timezone(&tz);
+                if round_up && zoned.minute() > 45 {
+                    zoned += ChronoDuration::hours(1);
                 }
-                return format!(
-                    "{}-{}-{}",
-                    local_dt.month(),
-                    local_dt.day(),
-                    local_dt.hour()
-                );
+                zoned.format(pattern).to_string()
+            }
+            None => {
+                let mut zoned = dt.with_timezone(&Local);
+                if round_up && zoned.minute() > 45 {
+                    zoned += ChronoDuration::hours(1);
+                }
+                zoned.format(pattern).to_string()
             }
         }
-        "?".to_string()
     }
 
     fn format_reset_duration(reset_time_str: Option<&str>) -> String {
@@ -159,6 +235,28 @@ impl UsageSegment {
         "?".to_string()
     }
 
+    /// Renders a sparkline over `samples` using the eight block glyphs,
+    /// chronological oldest-to-newest. Returns `None` when there are fewer
+    /// than two samples, so the caller can fall back to the numeric output.
+    fn render_sparkline(samples: &[UsageSample]) -> Option<String> {
+        const GLYPHS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+        if samples.len() < 2 {
+            return None;
+        }
+
+        Some(
+            samples
+                .iter()
+                .map(|sample| {
+                    let utilization = sample.five_hour_utilization.clamp(0.0, 100.0);
+                    let index = ((utilization / 100.0) * 7.0).round() as usize;
+                    GLYPHS[index.min(7)]
+                })
+                .collect(),
+        )
+    }
+
     fn get_cache_path() -> Option<std::path::PathBuf> {
         let home = dirs::home_dir()?;
         Some(
@@ -189,6 +287,218 @@ impl UsageSegment {
         Some(cache)
     }
 
+    /// Appends `sample` and trims the oldest entries beyond `MAX_SAMPLES`.
+    fn append_sample(samples: &mut Vec<UsageSample>, sample: UsageSample) {
+        samples.push(sample);
+        if samples.len() > MAX_SAMPLES {
+            let excess = samples.len() - MAX_SAMPLES;
+            samples.drain(0..excess);
+        }
+    }
+
+    /// True once `cached_at` is older than `cache_duration`, or unparseable.
+    fn cache_is_stale(cache: &ApiUsageCache, cache_duration: u64) -> bool {
+        let Ok(cached_at) = DateTime::parse_from_rfc3339(&cache.cached_at) else {
+            return true;
+        };
+        let age_secs = Utc::now()
+            .signed_duration_since(cached_at.with_timezone(&Utc))
+            .num_seconds();
+        age_secs < 0 || age_secs as u64 >= cache_duration
+    }
+
+    /// Builds the next on-disk cache from a successful fetch, carrying
+    /// forward and appending to `previous`'s sample history. Shared by the
+    /// background daemon and `collect`'s synchronous fallback so both paths
+    /// record samples the same way.
+    fn build_cache_from_fetch(
+        previous: Option<&ApiUsageCache>,
+        fetched: FetchedUsage,
+    ) -> ApiUsageCache {
+        let cached_at = Utc::now().to_rfc3339();
+        let mut samples = previous
+            .map(|cache| cache.samples.clone())
+            .unwrap_or_default();
+        Self::append_sample(
+            &mut samples,
+            UsageSample {
+                cached_at: cached_at.clone(),
+                five_hour_utilization: fetched.body.five_hour.utilization,
+                seven_day_utilization: fetched.body.seven_day.utilization,
+            },
+        );
+
+        ApiUsageCache {
+            five_hour_utilization: fetched.body.five_hour.utilization,
+            seven_day_utilization: fetched.body.seven_day.utilization,
+            five_hour_resets_at: fetched.body.five_hour.resets_at,
+            seven_day_resets_at: fetched.body.seven_day.resets_at,
+            legacy_resets_at: None,
+            cached_at,
+            auth_expired: false,
+            samples,
+            etag: fetched.etag,
+            last_modified: fetched.last_modified,
+        }
+    }
+
+    /// Returns a cache that is fresh enough to render, fetching synchronously
+    /// (bounded by `timeout_secs`) when the on-disk cache is missing or
+    /// stale. This is what keeps the segment correct even when the
+    /// best-effort background daemon (`spawn_background_daemon`) isn't
+    /// running: a per-render process can't rely on a detached thread to
+    /// finish a fetch after it exits, so a cold or expired cache must still
+    /// be refreshed in-line here.
+    fn load_synced_cache(
+        &self,
+        api_base_url: &str,
+        timeout_secs: u64,
+        cache_duration: u64,
+    ) -> Option<ApiUsageCache> {
+        let previous = self.load_cache();
+        if previous
+            .as_ref()
+            .is_some_and(|cache| !Self::cache_is_stale(cache, cache_duration))
+        {
+            return previous;
+        }
+
+        // We only get here when `previous` is missing or stale, so without a
+        // token to refresh it there is nothing trustworthy left to show:
+        // hide the segment instead of rendering an indefinitely-stale
+        // utilization number, matching the old behavior of hiding whenever
+        // no OAuth token is present.
+        let token = credentials::get_oauth_token()?;
+
+        let outcome = self.fetch_api_usage(
+            api_base_url,
+            &token,
+            timeout_secs,
+            previous.as_ref().and_then(|c| c.etag.as_deref()),
+            previous.as_ref().and_then(|c| c.last_modified.as_deref()),
+        );
+
+        match outcome {
+            UsageFetchOutcome::Success(fetched) => {
+                let cache = Self::build_cache_from_fetch(previous.as_ref(), fetched);
+                self.save_cache(&cache);
+                Some(cache)
+            }
+            UsageFetchOutcome::NotModified => previous.map(|mut cache| {
+                cache.cached_at = Utc::now().to_rfc3339();
+                self.save_cache(&cache);
+                cache
+            }),
+            UsageFetchOutcome::AuthExpired => previous.map(|mut cache| {
+                cache.auth_expired = true;
+                self.save_cache(&cache);
+                cache
+            }),
+            UsageFetchOutcome::Failed => previous,
+        }
+    }
+
+    fn get_daemon_lock_path() -> Option<std::path::PathBuf> {
+        let home = dirs::home_dir()?;
+        Some(
+            home.join(".claude")
+                .join("ccline")
+                .join(".usage_daemon.lock"),
+        )
+    }
+
+    fn get_daemon_last_render_path() -> Option<std::path::PathBuf> {
+        let home = dirs::home_dir()?;
+        Some(
+            home.join(".claude")
+                .join("ccline")
+                .join(".usage_daemon_last_render"),
+        )
+    }
+
+    /// Records that a render just happened, so a live daemon can tell whether
+    /// anything still wants it and self-exit once nothing has rendered for
+    /// [`DAEMON_IDLE_EXIT_SECS`].
+    fn touch_last_render() {
+        if let Some(path) = Self::get_daemon_last_render_path() {
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let _ = std::fs::write(&path, b"");
+        }
+    }
+
+    /// Cheap pre-filter for whether to bother forking a new daemon at all:
+    /// reads as fresh whenever a live daemon is heartbeating (see
+    /// `UsageRefreshDaemon::reclaim_lock`), which happens on roughly the same
+    /// cadence as `cache_duration`. The actual singleton guarantee lives in
+    /// the daemon's own claim/heartbeat dance, not here, since this alone
+    /// can't stop two renders from forking at nearly the same instant.
+    fn daemon_lock_is_fresh(cache_duration: u64) -> bool {
+        let Some(lock_path) = Self::get_daemon_lock_path() else {
+            return false;
+        };
+        std::fs::metadata(&lock_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < StdDuration::from_secs(cache_duration))
+    }
+
+    /// Best-effort: forks a detached `ccline --usage-daemon` process to keep
+    /// the cache warm between renders, so the next render is less likely to
+    /// hit `load_synced_cache`'s synchronous path. Skips the spawn if the
+    /// lock looks fresh. `collect` does not depend on this succeeding; see
+    /// `load_synced_cache`. A race that forks two daemons in the same window
+    /// still converges to one survivor, since the spawned daemon enforces
+    /// the real one-at-a-time guarantee itself via
+    /// `UsageRefreshDaemon::claim_lock`.
+    fn spawn_background_daemon(cache_duration: u64) {
+        if Self::daemon_lock_is_fresh(cache_duration) {
+            return;
+        }
+
+        let Ok(exe) = std::env::current_exe() else {
+            return;
+        };
+
+        let _ = std::process::Command::new(exe)
+            .arg(USAGE_DAEMON_FLAG)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+    }
+
+    /// Entry point for the detached `ccline --usage-daemon` process spawned
+    /// by `spawn_background_daemon`. `main` must check `std::env::args()` for
+    /// [`USAGE_DAEMON_FLAG`] before normal startup and dispatch here instead
+    /// of rendering a statusline. Reads the same segment options `collect`
+    /// does and then runs the refresh loop until the daemon is superseded or
+    /// goes idle (see [`DAEMON_IDLE_EXIT_SECS`]).
+    pub fn run_daemon() {
+        let config = crate::config::Config::load().ok();
+        let segment_config = config
+            .as_ref()
+            .and_then(|c| c.segments.iter().find(|s| s.id == SegmentId::Usage));
+
+        let api_base_url = segment_config
+            .and_then(|sc| sc.options.get("api_base_url"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("https://api.anthropic.com")
+            .to_string();
+        let cache_duration = segment_config
+            .and_then(|sc| sc.options.get("cache_duration"))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(300);
+        let timeout = segment_config
+            .and_then(|sc| sc.options.get("timeout"))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(2);
+
+        UsageRefreshDaemon::run(api_base_url, timeout, cache_duration);
+    }
+
     fn save_cache(&self, cache: &ApiUsageCache) {
         if let Some(cache_path) = Self::get_cache_path() {
             if let Some(parent) = cache_path.parent() {
@@ -200,16 +510,6 @@ impl UsageSegment {
         }
     }
 
-    fn is_cache_valid(&self, cache: &ApiUsageCache, cache_duration: u64) -> bool {
-        if let Ok(cached_at) = DateTime::parse_from_rfc3339(&cache.cached_at) {
-            let now = Utc::now();
-            let elapsed = now.signed_duration_since(cached_at.with_timezone(&Utc));
-            elapsed.num_seconds() < cache_duration as i64
-        } else {
-            false
-        }
-    }
-
     fn get_claude_code_version() -> String {
         use std::process::Command;
 
@@ -248,15 +548,7 @@ impl UsageSegment {
             .map(|s| s.to_string())
     }
 
-    fn fetch_api_usage(
-        &self,
-        api_base_url: &str,
-        token: &str,
-        timeout_secs: u64,
-    ) -> Option<ApiUsageResponse> {
-        let url = format!("{}/api/oauth/usage", api_base_url);
-        let user_agent = Self::get_claude_code_version();
-
+    fn build_usage_agent() -> ureq::Agent {
         let mut agent_builder = ureq::AgentBuilder::new();
 
         // Configure proxy from Claude settings if available
@@ -266,29 +558,376 @@ impl UsageSegment {
             }
         }
 
-        let agent = agent_builder.build();
+        agent_builder.build()
+    }
+
+    fn request_usage(
+        api_base_url: &str,
+        token: &SecretString,
+        timeout_secs: u64,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Option<ureq::Response> {
+        let url = format!("{}/api/oauth/usage", api_base_url);
+        let user_agent = Self::get_claude_code_version();
 
-        let response = agent
+        // The raw token is only ever materialized here, for the header value
+        // of this single request; `set` copies it into the request, so it's
+        // zeroized below instead of waiting on drop to retain it no longer
+        // than necessary.
+        let mut authorization = format!("Bearer {}", token.expose_secret());
+
+        let mut request = Self::build_usage_agent()
             .get(&url)
-            .set("Authorization", &format!("Bearer {}", token))
+            .set("Authorization", &authorization)
             .set("anthropic-beta", "oauth-2025-04-20")
             .set("User-Agent", &user_agent)
-            .timeout(std::time::Duration::from_secs(timeout_secs))
-            .call()
-            .ok()?;
+            .set("Accept-Encoding", "gzip")
+            .timeout(StdDuration::from_secs(timeout_secs));
+        authorization.zeroize();
 
-        if response.status() == 200 {
-            response.into_json().ok()
+        if let Some(etag) = etag {
+            request = request.set("If-None-Match", etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.set("If-Modified-Since", last_modified);
+        }
+
+        // ureq treats 4xx/5xx as an `Err` that still carries the response,
+        // which we need below to distinguish e.g. 401/403 from a dropped
+        // connection.
+        match request.call() {
+            Ok(response) => Some(response),
+            Err(ureq::Error::Status(_, response)) => Some(response),
+            Err(_) => None,
+        }
+    }
+
+    /// Parses a successful usage response body, transparently decoding it if
+    /// the server sent it gzip-compressed.
+    fn parse_usage_response(response: ureq::Response) -> Option<FetchedUsage> {
+        let etag = response.header("etag").map(str::to_string);
+        let last_modified = response.header("last-modified").map(str::to_string);
+        let is_gzip = response
+            .header("content-encoding")
+            .is_some_and(|value| value.eq_ignore_ascii_case("gzip"));
+
+        let body: ApiUsageResponse = if is_gzip {
+            let decoder = flate2::read::GzDecoder::new(response.into_reader());
+            serde_json::from_reader(decoder).ok()?
         } else {
-            None
+            response.into_json().ok()?
+        };
+
+        Some(FetchedUsage {
+            body,
+            etag,
+            last_modified,
+        })
+    }
+
+    /// Exchanges the stored refresh token for a fresh access/refresh pair via
+    /// the OAuth token endpoint, persisting the result back to the
+    /// credentials store.
+    fn refresh_oauth_token() -> Option<SecretString> {
+        #[derive(Deserialize)]
+        struct OAuthTokenResponse {
+            access_token: String,
+            refresh_token: String,
+        }
+
+        let refresh_token = credentials::get_refresh_token()?;
+        let body = serde_json::json!({
+            "grant_type": "refresh_token",
+            "refresh_token": refresh_token.expose_secret(),
+            "client_id": OAUTH_CLIENT_ID,
+        });
+
+        let response = Self::build_usage_agent()
+            .post(OAUTH_TOKEN_URL)
+            .set("anthropic-beta", "oauth-2025-04-20")
+            .send_json(body)
+            .ok()?;
+
+        if response.status() != 200 {
+            return None;
+        }
+
+        let refreshed: OAuthTokenResponse = response.into_json().ok()?;
+        credentials::save_oauth_tokens(&refreshed.access_token, &refreshed.refresh_token);
+
+        Some(SecretString::new(refreshed.access_token))
+    }
+
+    /// Fetches usage, transparently refreshing the access token once if the
+    /// API reports it as expired. Guarded to a single retry so a broken
+    /// refresh token can never loop.
+    fn fetch_api_usage(
+        &self,
+        api_base_url: &str,
+        token: &SecretString,
+        timeout_secs: u64,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> UsageFetchOutcome {
+        let Some(response) =
+            Self::request_usage(api_base_url, token, timeout_secs, etag, last_modified)
+        else {
+            return UsageFetchOutcome::Failed;
+        };
+
+        match response.status() {
+            200 => Self::parse_usage_response(response)
+                .map(UsageFetchOutcome::Success)
+                .unwrap_or(UsageFetchOutcome::Failed),
+            304 => UsageFetchOutcome::NotModified,
+            401 | 403 => {
+                let Some(refreshed_token) = Self::refresh_oauth_token() else {
+                    return UsageFetchOutcome::AuthExpired;
+                };
+
+                // Re-authenticating invalidates any cached validators, so
+                // this retry always asks for a full, uncached body.
+                match Self::request_usage(api_base_url, &refreshed_token, timeout_secs, None, None)
+                {
+                    Some(retry_response) if retry_response.status() == 200 => {
+                        Self::parse_usage_response(retry_response)
+                            .map(UsageFetchOutcome::Success)
+                            .unwrap_or(UsageFetchOutcome::Failed)
+                    }
+                    _ => UsageFetchOutcome::AuthExpired,
+                }
+            }
+            _ => UsageFetchOutcome::Failed,
         }
     }
 }
 
+/// A successfully parsed usage body, plus the cache validators the server
+/// sent alongside it.
+struct FetchedUsage {
+    body: ApiUsageResponse,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Outcome of a usage fetch, distinguishing an expired/unrefreshable token
+/// and an unchanged (304) response from an ordinary transient failure so
+/// callers can handle each differently.
+enum UsageFetchOutcome {
+    Success(FetchedUsage),
+    NotModified,
+    AuthExpired,
+    Failed,
+}
+
+/// Minimum time between two refreshes, even if a reset boundary is imminent.
+const MIN_REFRESH_INTERVAL_SECS: u64 = 5;
+/// Ceiling for the exponential backoff applied after a failed fetch.
+const MAX_BACKOFF_SECS: u64 = 300;
+/// How many recent samples the `trend` sparkline keeps around.
+const MAX_SAMPLES: usize = 24;
+
+/// How long a freshly forked daemon waits before re-reading the lock to see
+/// whether a sibling spawned in the same window won the claim race instead.
+const DAEMON_CLAIM_SETTLE: StdDuration = StdDuration::from_millis(250);
+/// A daemon that hasn't seen a render touch `.usage_daemon_last_render` for
+/// this long assumes nothing needs it anymore and exits; the next render to
+/// come along will fork a fresh one.
+const DAEMON_IDLE_EXIT_SECS: u64 = 3600;
+
+/// CLI flag that, when passed to the `ccline` binary, runs
+/// [`UsageSegment::run_daemon`] instead of rendering a statusline.
+pub const USAGE_DAEMON_FLAG: &str = "--usage-daemon";
+
+/// Owns all network I/O for the out-of-process usage refresh daemon started
+/// via [`USAGE_DAEMON_FLAG`]. Runs in its own process, until superseded or
+/// idle for [`DAEMON_IDLE_EXIT_SECS`], so a fetch can
+/// complete and be written to the cache even after the `ccline` render that
+/// spawned it has already exited. Enforces its own singleton guarantee
+/// (`claim_lock`/`reclaim_lock`) rather than trusting the best-effort spawn
+/// rate-limiting in `UsageSegment::spawn_background_daemon`.
+struct UsageRefreshDaemon;
+
+impl UsageRefreshDaemon {
+    /// Appends `.tmp` to `lock_path` for an atomic write-then-rename.
+    fn lock_tmp_path(lock_path: &std::path::Path) -> std::path::PathBuf {
+        let mut tmp = lock_path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        std::path::PathBuf::from(tmp)
+    }
+
+    /// Writes `pid` into the lock file via a temp-file-plus-rename so
+    /// concurrent readers never observe a half-written file.
+    fn write_lock_pid(lock_path: &std::path::Path, pid: u32) -> std::io::Result<()> {
+        if let Some(parent) = lock_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = Self::lock_tmp_path(lock_path);
+        std::fs::write(&tmp_path, pid.to_string())?;
+        std::fs::rename(&tmp_path, lock_path)
+    }
+
+    fn read_lock_pid(lock_path: &std::path::Path) -> Option<u32> {
+        std::fs::read_to_string(lock_path).ok()?.trim().parse().ok()
+    }
+
+    /// Claims the daemon lock for our own PID, waits out a short settle
+    /// window, then re-reads it. If another daemon forked in the same window
+    /// wrote its PID after ours, we lost the race and must not run.
+    fn claim_lock() -> bool {
+        let Some(lock_path) = UsageSegment::get_daemon_lock_path() else {
+            return true;
+        };
+        let pid = std::process::id();
+        if Self::write_lock_pid(&lock_path, pid).is_err() {
+            return true;
+        }
+
+        std::thread::sleep(DAEMON_CLAIM_SETTLE);
+
+        match Self::read_lock_pid(&lock_path) {
+            Some(owner) => owner == pid,
+            None => true,
+        }
+    }
+
+    /// Re-asserts our claim (bumping the lock's mtime, which is also what
+    /// `UsageSegment::daemon_lock_is_fresh` reads as the heartbeat) and
+    /// reports whether we still hold it -- false means a newer daemon has
+    /// since taken over and we must exit.
+    fn reclaim_lock() -> bool {
+        let Some(lock_path) = UsageSegment::get_daemon_lock_path() else {
+            return true;
+        };
+        let pid = std::process::id();
+        match Self::read_lock_pid(&lock_path) {
+            Some(owner) if owner != pid => false,
+            _ => Self::write_lock_pid(&lock_path, pid).is_ok(),
+        }
+    }
+
+    /// True once nothing has rendered for [`DAEMON_IDLE_EXIT_SECS`], so the
+    /// daemon knows it's safe to exit instead of polling forever.
+    fn is_idle() -> bool {
+        UsageSegment::get_daemon_last_render_path()
+            .and_then(|path| std::fs::metadata(&path).ok())
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= StdDuration::from_secs(DAEMON_IDLE_EXIT_SECS))
+    }
+
+    /// Drives a min-ordered schedule of refresh instants: pop the earliest
+    /// entry, sleep until it is due, fetch, then re-insert the next run.
+    fn run(api_base_url: String, timeout_secs: u64, cache_duration: u64) {
+        if !Self::claim_lock() {
+            // Lost the startup race to a sibling daemon spawned in the same
+            // window; that instance is now the singleton, so step aside.
+            return;
+        }
+
+        let segment = UsageSegment::new();
+        let mut schedule: BTreeMap<Instant, ()> = BTreeMap::new();
+        schedule.insert(Instant::now(), ());
+        let mut backoff_secs = 1u64;
+
+        loop {
+            let due = match schedule.keys().next().copied() {
+                Some(instant) => instant,
+                None => Instant::now(),
+            };
+            schedule.remove(&due);
+
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+
+            if !Self::reclaim_lock() {
+                return;
+            }
+            if Self::is_idle() {
+                return;
+            }
+
+            let Some(token) = credentials::get_oauth_token() else {
+                schedule.insert(Instant::now() + StdDuration::from_secs(cache_duration), ());
+                continue;
+            };
+
+            let previous_cache = segment.load_cache();
+            let outcome = segment.fetch_api_usage(
+                &api_base_url,
+                &token,
+                timeout_secs,
+                previous_cache.as_ref().and_then(|c| c.etag.as_deref()),
+                previous_cache
+                    .as_ref()
+                    .and_then(|c| c.last_modified.as_deref()),
+            );
+
+            match outcome {
+                UsageFetchOutcome::Success(fetched) => {
+                    backoff_secs = 1;
+                    let next_interval = Self::next_refresh_interval(cache_duration, &fetched.body);
+                    let cache =
+                        UsageSegment::build_cache_from_fetch(previous_cache.as_ref(), fetched);
+                    segment.save_cache(&cache);
+
+                    schedule.insert(Instant::now() + next_interval, ());
+                }
+                UsageFetchOutcome::NotModified => {
+                    backoff_secs = 1;
+                    if let Some(mut cache) = previous_cache {
+                        cache.cached_at = Utc::now().to_rfc3339();
+                        segment.save_cache(&cache);
+                    }
+                    schedule.insert(Instant::now() + StdDuration::from_secs(cache_duration), ());
+                }
+                UsageFetchOutcome::AuthExpired => {
+                    if let Some(mut cache) = previous_cache {
+                        cache.auth_expired = true;
+                        segment.save_cache(&cache);
+                    }
+                    schedule.insert(Instant::now() + StdDuration::from_secs(backoff_secs), ());
+                    backoff_secs = (backoff_secs * 2).min(MAX_BACKOFF_SECS);
+                }
+                UsageFetchOutcome::Failed => {
+                    schedule.insert(Instant::now() + StdDuration::from_secs(backoff_secs), ());
+                    backoff_secs = (backoff_secs * 2).min(MAX_BACKOFF_SECS);
+                }
+            }
+        }
+    }
+
+    /// `min(cache_duration, time_until(resets_at))`, so the next refresh
+    /// lands sooner the closer we are to a reset boundary.
+    fn next_refresh_interval(cache_duration: u64, response: &ApiUsageResponse) -> StdDuration {
+        let seconds_until_reset = response
+            .five_hour
+            .resets_at
+            .as_deref()
+            .or(response.seven_day.resets_at.as_deref())
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|reset_at| {
+                reset_at
+                    .with_timezone(&Utc)
+                    .signed_duration_since(Utc::now())
+                    .num_seconds()
+                    .max(0) as u64
+            });
+
+        let interval_secs = match seconds_until_reset {
+            Some(secs) => cache_duration.min(secs),
+            None => cache_duration,
+        };
+
+        StdDuration::from_secs(interval_secs.max(MIN_REFRESH_INTERVAL_SECS))
+    }
+}
+
 impl Segment for UsageSegment {
     fn collect(&self, _input: &InputData) -> Option<SegmentData> {
-        let token = credentials::get_oauth_token()?;
-
         // Load config from file to get segment options
         let config = crate::config::Config::load().ok()?;
         let segment_config = config.segments.iter().find(|s| s.id == SegmentId::Usage);
@@ -308,11 +947,14 @@ impl Segment for UsageSegment {
             .and_then(|v| v.as_u64())
             .unwrap_or(2);
 
-        let cached_data = self.load_cache();
-        let use_cached = cached_data
-            .as_ref()
-            .map(|cache| self.is_cache_valid(cache, cache_duration))
-            .unwrap_or(false);
+        // Best-effort: keeps the cache warm between renders via a detached
+        // daemon process. collect does not depend on this succeeding, since
+        // load_synced_cache below falls back to a synchronous fetch whenever
+        // the cache it left behind is missing or stale.
+        Self::touch_last_render();
+        Self::spawn_background_daemon(cache_duration);
+
+        let cached_data = self.load_synced_cache(api_base_url, timeout, cache_duration)?;
 
         let reset_period_raw = segment_config
             .and_then(|sc| sc.options.get("reset_period"))
@@ -334,51 +976,42 @@ impl Segment for UsageSegment {
             .and_then(|value| ResetFormat::try_from(value).ok())
             .unwrap_or_default();
 
-        let (five_hour_util, seven_day_util, five_hour_resets_at, seven_day_resets_at) =
-            if use_cached {
-                if let Some(cache) = cached_data.as_ref() {
-                    (
-                        cache.five_hour_utilization,
-                        cache.seven_day_utilization,
-                        cache.five_hour_resets_at.clone(),
-                        cache.seven_day_resets_at.clone(),
-                    )
-                } else {
-                    return None;
-                }
-            } else {
-                match self.fetch_api_usage(api_base_url, &token, timeout) {
-                    Some(response) => {
-                        let cache = ApiUsageCache {
-                            five_hour_utilization: response.five_hour.utilization,
-                            seven_day_utilization: response.seven_day.utilization,
-                            five_hour_resets_at: response.five_hour.resets_at.clone(),
-                            seven_day_resets_at: response.seven_day.resets_at.clone(),
-                            legacy_resets_at: None,
-                            cached_at: Utc::now().to_rfc3339(),
-                        };
-                        self.save_cache(&cache);
-                        (
-                            response.five_hour.utilization,
-                            response.seven_day.utilization,
-                            response.five_hour.resets_at,
-                            response.seven_day.resets_at,
-                        )
-                    }
-                    None => {
-                        if let Some(cache) = cached_data {
-                            (
-                                cache.five_hour_utilization,
-                                cache.seven_day_utilization,
-                                cache.five_hour_resets_at,
-                                cache.seven_day_resets_at,
-                            )
-                        } else {
-                            return None;
-                        }
-                    }
-                }
-            };
+        let reset_timezone_raw = segment_config
+            .and_then(|sc| sc.options.get("reset_timezone"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        let reset_timezone = reset_timezone_raw
+            .as_deref()
+            .and_then(|value| Self::resolve_timezone(value).ok());
+
+        let reset_time_pattern_raw = segment_config
+            .and_then(|sc| sc.options.get("reset_time_pattern"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        let reset_time_pattern = reset_time_pattern_raw
+            .as_deref()
+            .filter(|pattern| Self::is_valid_time_pattern(pattern))
+            .unwrap_or(DEFAULT_RESET_TIME_PATTERN);
+
+        // Behavior change: this used to always round up past :45. Defaulting
+        // to off means existing users who never set this option will see a
+        // different displayed reset time than before; call this out in
+        // release notes, since it's a visible change and not a no-op.
+        let round_up_minutes = segment_config
+            .and_then(|sc| sc.options.get("round_up_minutes"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let auth_expired = cached_data.auth_expired;
+        let sparkline = Self::render_sparkline(&cached_data.samples);
+        let (five_hour_util, seven_day_util, five_hour_resets_at, seven_day_resets_at) = (
+            cached_data.five_hour_utilization,
+            cached_data.seven_day_utilization,
+            cached_data.five_hour_resets_at,
+            cached_data.seven_day_resets_at,
+        );
 
         let resets_at = match reset_period {
             ResetPeriod::Session => five_hour_resets_at
@@ -394,12 +1027,30 @@ impl Segment for UsageSegment {
         let primary = format!("{}%", five_hour_percent);
         let reset_str = match reset_format {
             ResetFormat::Duration => Self::format_reset_duration(resets_at),
-            ResetFormat::Time => Self::format_reset_time(resets_at),
+            ResetFormat::Time => Self::format_reset_time(
+                resets_at,
+                reset_timezone,
+                reset_time_pattern,
+                round_up_minutes,
+            ),
+            // Too few samples to draw a meaningful trend yet; fall back to
+            // the current numeric output instead of an empty/flat sparkline.
+            ResetFormat::Trend => sparkline.clone().unwrap_or_else(|| {
+                Self::format_reset_time(
+                    resets_at,
+                    reset_timezone,
+                    reset_time_pattern,
+                    round_up_minutes,
+                )
+            }),
         };
         let secondary = format!("Â· {}", reset_str);
 
         let mut metadata = HashMap::new();
         metadata.insert("dynamic_icon".to_string(), dynamic_icon);
+        if let Some(sparkline) = sparkline {
+            metadata.insert("usage_sparkline".to_string(), sparkline);
+        }
         metadata.insert(
             "five_hour_utilization".to_string(),
             five_hour_util.to_string(),
@@ -434,6 +1085,24 @@ impl Segment for UsageSegment {
                 invalid_reset_format.to_string(),
             );
         }
+        if auth_expired {
+            metadata.insert("auth_expired".to_string(), "true".to_string());
+        }
+        if let Some(invalid_timezone) = reset_timezone_raw
+            .as_deref()
+            .filter(|value| Self::resolve_timezone(value).is_err())
+        {
+            metadata.insert("invalid_timezone".to_string(), invalid_timezone.to_string());
+        }
+        if let Some(invalid_time_pattern) = reset_time_pattern_raw
+            .as_deref()
+            .filter(|pattern| !Self::is_valid_time_pattern(pattern))
+        {
+            metadata.insert(
+                "invalid_time_pattern".to_string(),
+                invalid_time_pattern.to_string(),
+            );
+        }
 
         Some(SegmentData {
             primary,
@@ -449,8 +1118,8 @@ impl Segment for UsageSegment {
 
 #[cfg(test)]
 mod tests {
-    use super::{ResetFormat, ResetPeriod, UsageSegment};
-    use chrono::{Duration, Utc};
+    use super::{ResetFormat, ResetPeriod, UsageSample, UsageSegment};
+    use chrono::{Duration as ChronoDuration, Utc};
 
     #[test]
     fn reset_period_parses_expected_values() {
@@ -475,14 +1144,92 @@ mod tests {
             ResetFormat::try_from("DURATION"),
             Ok(ResetFormat::Duration)
         ));
+        assert!(matches!(
+            ResetFormat::try_from("TREND"),
+            Ok(ResetFormat::Trend)
+        ));
         assert!(ResetFormat::try_from("invalid").is_err());
     }
 
     #[test]
     fn duration_under_one_minute_is_now() {
-        let reset_at = (Utc::now() + Duration::seconds(30)).to_rfc3339();
+        let reset_at = (Utc::now() + ChronoDuration::seconds(30)).to_rfc3339();
         let display = UsageSegment::format_reset_duration(Some(&reset_at));
 
         assert_eq!(display, "now");
     }
+
+    #[test]
+    fn resolve_timezone_accepts_utc_and_iana_names() {
+        assert_eq!(UsageSegment::resolve_timezone("utc"), Ok(chrono_tz::UTC));
+        assert_eq!(
+            UsageSegment::resolve_timezone("America/New_York"),
+            Ok(chrono_tz::America::New_York)
+        );
+        assert!(UsageSegment::resolve_timezone("not_a_timezone").is_err());
+    }
+
+    #[test]
+    fn time_pattern_validity_mirrors_chrono() {
+        assert!(UsageSegment::is_valid_time_pattern("%Y-%m-%d %H:%M"));
+        assert!(!UsageSegment::is_valid_time_pattern("%Q"));
+    }
+
+    #[test]
+    fn format_reset_time_uses_explicit_timezone_and_pattern() {
+        let reset_at = "2026-07-26T09:30:00Z";
+        let display = UsageSegment::format_reset_time(
+            Some(reset_at),
+            Some(chrono_tz::UTC),
+            "%Y-%m-%d %H:%M",
+            false,
+        );
+
+        assert_eq!(display, "2026-07-26 09:30");
+    }
+
+    #[test]
+    fn format_reset_time_round_up_is_opt_in() {
+        let reset_at = "2026-07-26T09:50:00Z";
+
+        let without_round_up =
+            UsageSegment::format_reset_time(Some(reset_at), Some(chrono_tz::UTC), "%H", false);
+        assert_eq!(without_round_up, "09");
+
+        let with_round_up =
+            UsageSegment::format_reset_time(Some(reset_at), Some(chrono_tz::UTC), "%H", true);
+        assert_eq!(with_round_up, "10");
+    }
+
+    fn sample(five_hour_utilization: f64) -> UsageSample {
+        UsageSample {
+            cached_at: Utc::now().to_rfc3339(),
+            five_hour_utilization,
+            seven_day_utilization: five_hour_utilization,
+        }
+    }
+
+    #[test]
+    fn sparkline_falls_back_with_fewer_than_two_samples() {
+        assert_eq!(UsageSegment::render_sparkline(&[]), None);
+        assert_eq!(UsageSegment::render_sparkline(&[sample(50.0)]), None);
+    }
+
+    #[test]
+    fn sparkline_maps_utilization_onto_block_glyphs() {
+        let samples = vec![sample(0.0), sample(50.0), sample(100.0)];
+        assert_eq!(
+            UsageSegment::render_sparkline(&samples),
+            Some("▁▅█".to_string())
+        );
+    }
+
+    #[test]
+    fn sparkline_clamps_out_of_range_utilization() {
+        let samples = vec![sample(-10.0), sample(150.0)];
+        assert_eq!(
+            UsageSegment::render_sparkline(&samples),
+            Some("▁█".to_string())
+        );
+    }
 }